@@ -6,6 +6,8 @@
 use shopify_function::prelude::*;
 use shopify_function::Result;
 
+mod tag;
+
 /// Entry point for the delivery customization function
 ///
 /// # Arguments
@@ -62,9 +64,10 @@ fn run(input: input::ResponseData) -> Result<output::FunctionRunResult> {
 /// Determines whether a delivery option should be hidden for a given variant
 ///
 /// # Pattern
-/// Options tagged with a variant suffix (e.g., "Standard Shipping (A)") will only
-/// be shown to customers assigned to that variant. Options without a suffix are
-/// shown to all variants.
+/// Options tagged with a variant label (e.g., "Standard Shipping (A)",
+/// "(Control) Express") are only shown to customers assigned to one of the
+/// tagged variants - a title may carry several, comma-separated. Options
+/// without a tag are shown to all variants. See [`tag`] for the grammar.
 ///
 /// # Arguments
 /// * `title` - The delivery option title
@@ -73,72 +76,21 @@ fn run(input: input::ResponseData) -> Result<output::FunctionRunResult> {
 /// # Returns
 /// * `true` if the option should be hidden from this variant
 fn should_hide_option(title: &str, variant: &str) -> bool {
-    // Check if the title contains a variant suffix
-    if let Some(suffix) = extract_variant_suffix(title) {
-        // Hide if suffix doesn't match the assigned variant
-        return suffix != variant;
-    }
-
-    // Options without suffix are shown to all variants
-    false
-}
-
-/// Extracts the variant suffix from a delivery option title
-///
-/// # Pattern
-/// Looks for " (X)" at the end of the title where X is a single alphanumeric character.
-/// Examples:
-/// - "Standard Shipping (A)" -> Some("A")
-/// - "Express Shipping (B)" -> Some("B")
-/// - "Free Shipping" -> None
-/// - "Standard Shipping (Control)" -> None (too long)
-///
-/// # Arguments
-/// * `title` - The delivery option title
-///
-/// # Returns
-/// * `Some(&str)` containing the variant character if found
-/// * `None` if no valid variant suffix
-fn extract_variant_suffix(title: &str) -> Option<&str> {
-    // Minimum length check: need at least " (X)" = 4 chars
-    if title.len() < 4 {
-        return None;
-    }
-
-    let bytes = title.as_bytes();
-    let len = bytes.len();
+    let labels = tag::parse(title, &[]);
 
-    // Check for pattern: space, open paren, single char, close paren at end
-    if bytes[len - 1] == b')' && bytes[len - 3] == b'(' && bytes[len - 4] == b' ' {
-        let variant_byte = bytes[len - 2];
-        // Only accept single alphanumeric characters as variants
-        if variant_byte.is_ascii_alphanumeric() {
-            return Some(&title[len - 2..len - 1]);
-        }
+    // Options without a tag are shown to all variants
+    if labels.is_empty() {
+        return false;
     }
 
-    None
+    // Hide unless the assigned variant is one of the tagged labels
+    !labels.iter().any(|label| label.eq_ignore_ascii_case(variant))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_extract_variant_suffix_valid() {
-        assert_eq!(extract_variant_suffix("Standard Shipping (A)"), Some("A"));
-        assert_eq!(extract_variant_suffix("Express (B)"), Some("B"));
-        assert_eq!(extract_variant_suffix("Test (1)"), Some("1"));
-    }
-
-    #[test]
-    fn test_extract_variant_suffix_invalid() {
-        assert_eq!(extract_variant_suffix("Standard Shipping"), None);
-        assert_eq!(extract_variant_suffix("Free Shipping"), None);
-        assert_eq!(extract_variant_suffix("Standard (AB)"), None); // Too long
-        assert_eq!(extract_variant_suffix("(A)"), None); // Too short
-    }
-
     #[test]
     fn test_should_hide_option() {
         // Variant A should see option A, hide option B
@@ -153,4 +105,15 @@ mod tests {
         assert!(!should_hide_option("Express Shipping", "A"));
         assert!(!should_hide_option("Express Shipping", "B"));
     }
+
+    #[test]
+    fn test_should_hide_option_multi_label_and_brackets() {
+        // A rate shared by several variants is shown to all of them
+        assert!(!should_hide_option("Standard Shipping (A,C)", "C"));
+        assert!(should_hide_option("Standard Shipping (A,C)", "B"));
+
+        // Bracket tags and multi-character, case-insensitive labels work too
+        assert!(!should_hide_option("[Control] Express", "control"));
+        assert!(!should_hide_option("Express (B2)", "b2"));
+    }
 }
@@ -0,0 +1,163 @@
+//! Variant-tag parsing grammar, shared by every place that reads an A/B
+//! variant label off a delivery option title.
+//!
+//! A tag is a comma-separated list of 1-N character alphanumeric labels,
+//! enclosed in `()` or `[]`, at either the start or the end of a title:
+//! "Standard (A)", "(Control) Standard", "Standard [B2]", and
+//! "Standard (A,C)" for a rate shared by several variants.
+//!
+//! This replaces two hand-rolled extractors that used to disagree with each
+//! other and reject anything but a single `A`-`D` character.
+//!
+//! STOPGAP: this module is intentionally duplicated, identical apart from
+//! this path, at `shopify-app/extensions/shipping-ab-test/src/tag.rs`. Neither
+//! extension's crate has a manifest in this tree, so there's no workspace
+//! to hang a real shared crate off of - pulling this into one is real work
+//! that's out of scope here. Until that lands, any change to this grammar
+//! must be mirrored in both copies by hand.
+
+/// Parses the variant labels tagged onto `title`.
+///
+/// If `known_labels` is non-empty it filters the result: only labels found
+/// in it survive, matched case-insensitively by binary search, so
+/// `known_labels` must already be sorted ascending (case-insensitively). An
+/// empty `known_labels` disables filtering - every syntactically valid
+/// label is returned.
+pub(crate) fn parse<'a>(title: &'a str, known_labels: &[String]) -> Vec<&'a str> {
+    let Some(group) = bracketed_group(title) else {
+        return Vec::new();
+    };
+
+    group
+        .split(',')
+        .map(str::trim)
+        .filter(|label| known_labels.is_empty() || contains_ci(known_labels, label))
+        .collect()
+}
+
+/// Removes a leading or trailing variant tag from `title`, if present,
+/// returning the bare display title. Unlike [`parse`] this doesn't filter
+/// by `known_labels` - any syntactically valid tag is stripped.
+pub(crate) fn strip(title: &str) -> String {
+    let trimmed = title.trim();
+
+    for (open, close) in [('(', ')'), ('[', ']')] {
+        if let Some(rest) = trimmed.strip_prefix(open) {
+            if let Some(end) = rest.find(close) {
+                if is_label_list(&rest[..end]) {
+                    return rest[end + 1..].trim_start().to_string();
+                }
+            }
+        }
+
+        if let Some(rest) = trimmed.strip_suffix(close) {
+            if let Some(start) = rest.rfind(open) {
+                if is_label_list(&rest[start + 1..]) {
+                    return rest[..start].trim_end().to_string();
+                }
+            }
+        }
+    }
+
+    trimmed.to_string()
+}
+
+/// Returns whether `sorted_labels` (ascending, case-insensitive) contains
+/// `label`, found by binary search via `partition_point` rather than a
+/// linear scan or a hardcoded `matches!`.
+pub(crate) fn contains_ci(sorted_labels: &[String], label: &str) -> bool {
+    let label_lower = label.to_ascii_lowercase();
+    let idx = sorted_labels.partition_point(|known| known.to_ascii_lowercase() < label_lower);
+    sorted_labels
+        .get(idx)
+        .is_some_and(|candidate| candidate.eq_ignore_ascii_case(label))
+}
+
+/// Extracts the contents of a leading or trailing `(...)` / `[...]` group,
+/// if its contents parse as a comma-separated label list.
+fn bracketed_group(title: &str) -> Option<&str> {
+    let trimmed = title.trim();
+
+    for (open, close) in [('(', ')'), ('[', ']')] {
+        if let Some(rest) = trimmed.strip_prefix(open) {
+            if let Some(end) = rest.find(close) {
+                if is_label_list(&rest[..end]) {
+                    return Some(&rest[..end]);
+                }
+            }
+        }
+
+        if let Some(rest) = trimmed.strip_suffix(close) {
+            if let Some(start) = rest.rfind(open) {
+                if is_label_list(&rest[start + 1..]) {
+                    return Some(&rest[start + 1..]);
+                }
+            }
+        }
+    }
+
+    None
+}
+
+/// A valid label list is non-empty, comma-separated, and every label is
+/// 1-N alphanumeric characters.
+fn is_label_list(s: &str) -> bool {
+    !s.is_empty()
+        && s.split(',').all(|label| {
+            let label = label.trim();
+            !label.is_empty() && label.chars().all(|c| c.is_ascii_alphanumeric())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_trailing_parens() {
+        assert_eq!(parse("Standard Shipping (A)", &[]), vec!["A"]);
+        assert_eq!(parse("Express (Control)", &[]), vec!["Control"]);
+    }
+
+    #[test]
+    fn test_parse_leading_brackets() {
+        assert_eq!(parse("(Control) Standard Shipping", &[]), vec!["Control"]);
+        assert_eq!(parse("[B2] Express", &[]), vec!["B2"]);
+    }
+
+    #[test]
+    fn test_parse_multi_label() {
+        assert_eq!(parse("Standard (A,C)", &[]), vec!["A", "C"]);
+        assert_eq!(parse("Standard (A, C)", &[]), vec!["A", "C"]);
+    }
+
+    #[test]
+    fn test_parse_no_tag() {
+        assert_eq!(parse("Free Shipping", &[]), Vec::<&str>::new());
+        assert_eq!(parse("Free Shipping ()", &[]), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn test_parse_filters_by_known_labels_case_insensitively() {
+        let known = vec!["a".to_string(), "control".to_string()];
+        assert_eq!(parse("Standard (A,Z)", &known), vec!["A"]);
+        assert_eq!(parse("Standard (CONTROL)", &known), vec!["CONTROL"]);
+        assert_eq!(parse("Standard (Z)", &known), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn test_strip_removes_leading_and_trailing_tags() {
+        assert_eq!(strip("Standard Shipping (A)"), "Standard Shipping");
+        assert_eq!(strip("(Control) Standard Shipping"), "Standard Shipping");
+        assert_eq!(strip("[B2] Express"), "Express");
+        assert_eq!(strip("Free Shipping"), "Free Shipping");
+    }
+
+    #[test]
+    fn test_contains_ci_binary_search() {
+        let sorted = vec!["a".to_string(), "b".to_string(), "control".to_string()];
+        assert!(contains_ci(&sorted, "A"));
+        assert!(contains_ci(&sorted, "Control"));
+        assert!(!contains_ci(&sorted, "d"));
+    }
+}
@@ -0,0 +1,88 @@
+//! Deterministic self-bucketing for carts with no explicit variant
+//! attribute.
+//!
+//! Without this, a missing variant attribute meant "show everything"
+//! (control), silently leaking the full option set to every untagged
+//! visitor. Instead we derive a stable variant from a hash of a
+//! cart-stable identifier, so the same visitor lands in the same bucket on
+//! every checkout reload without any storefront cooperation.
+
+/// Assigns a variant to `identifier` using a deterministic 64-bit FNV-1a
+/// hash and a weighted cumulative bucket table.
+///
+/// Pure function of `identifier` and `weights`, so the function stays
+/// idempotent: the same cart-stable identifier always yields the same
+/// variant. Returns `None` if `weights` carries no weight to bucket into.
+pub(crate) fn assign_variant<'a>(identifier: &str, weights: &'a [(String, u32)]) -> Option<&'a str> {
+    let total_weight: u64 = weights.iter().map(|(_, weight)| *weight as u64).sum();
+    if total_weight == 0 {
+        return None;
+    }
+
+    let remainder = fnv1a(identifier.as_bytes()) % total_weight;
+    bucket_for(remainder, weights)
+}
+
+/// Walks the cumulative-weight table, returning the first variant whose
+/// running sum exceeds `remainder`.
+fn bucket_for<'a>(remainder: u64, weights: &'a [(String, u32)]) -> Option<&'a str> {
+    let mut running = 0u64;
+    for (variant, weight) in weights {
+        running += *weight as u64;
+        if remainder < running {
+            return Some(variant);
+        }
+    }
+    None
+}
+
+/// 64-bit FNV-1a hash.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+
+    bytes
+        .iter()
+        .fold(OFFSET_BASIS, |hash, &byte| (hash ^ byte as u64).wrapping_mul(PRIME))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fnv1a_known_vectors() {
+        assert_eq!(fnv1a(b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+    }
+
+    #[test]
+    fn test_bucket_for_boundaries() {
+        let weights = vec![("A".to_string(), 1), ("B".to_string(), 3)];
+        assert_eq!(bucket_for(0, &weights), Some("A"));
+        assert_eq!(bucket_for(1, &weights), Some("B"));
+        assert_eq!(bucket_for(3, &weights), Some("B"));
+        assert_eq!(bucket_for(4, &weights), None);
+    }
+
+    #[test]
+    fn test_assign_variant_is_stable_for_same_identifier() {
+        let weights = vec![("A".to_string(), 1), ("B".to_string(), 1)];
+        assert_eq!(
+            assign_variant("cart-123", &weights),
+            assign_variant("cart-123", &weights)
+        );
+    }
+
+    #[test]
+    fn test_assign_variant_distributes_across_variants() {
+        let weights = vec![("A".to_string(), 1), ("B".to_string(), 1)];
+        assert_eq!(assign_variant("cart-123", &weights), Some("A"));
+        assert_eq!(assign_variant("cart-456", &weights), Some("B"));
+    }
+
+    #[test]
+    fn test_assign_variant_empty_weights_is_none() {
+        assert_eq!(assign_variant("cart-123", &[]), None);
+    }
+}
@@ -0,0 +1,252 @@
+//! Declarative variant-targeting configuration.
+//!
+//! Historically the mapping between delivery options and A/B variants was
+//! hardcoded into the title convention (a trailing " (A)" suffix). This
+//! module instead lets merchants provide a JSON document through the
+//! function's configuration metafield, naming which variants are eligible
+//! to see each delivery option along with optional display order and rename
+//! text, plus optional bucket weights for self-bucketing carts (see
+//! [`crate::assign`]). This supports multi-rate-per-variant setups and
+//! tests that don't require renaming carrier rates, neither of which the
+//! suffix convention can express.
+
+use serde::Deserialize;
+
+use crate::run::VariantOp;
+
+/// Targeting rules read from the function's configuration metafield.
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct TargetingConfig {
+    rules: Vec<TargetingRule>,
+    /// Bucket weights for self-bucketing carts with no variant attribute.
+    /// Defaults to equal weight across every variant named in `rules` when
+    /// absent, or across `DEFAULT_KNOWN_VARIANTS` if `rules` names none.
+    #[serde(default)]
+    weights: Vec<VariantWeight>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct VariantWeight {
+    variant: String,
+    #[serde(default = "default_weight")]
+    weight: u32,
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct TargetingRule {
+    /// Delivery-option handle, or a substring to match against the title.
+    #[serde(rename = "match")]
+    matcher: String,
+    /// Variants eligible to see this option.
+    variants: Vec<String>,
+    /// Target index within the delivery group, for eligible variants.
+    #[serde(default)]
+    index: Option<usize>,
+    /// Replacement title shown to eligible variants.
+    #[serde(default)]
+    rename: Option<String>,
+}
+
+impl TargetingConfig {
+    /// Parses the config from the raw JSON stored in the metafield value.
+    /// Returns `None` on missing or invalid input so callers can fall back
+    /// to the suffix convention.
+    pub(crate) fn parse(raw: &str) -> Option<Self> {
+        serde_json::from_str(raw).ok()
+    }
+
+    /// Resolves the ops to apply to a delivery option for the given variant,
+    /// or `None` if no rule in this config matches the option (the caller
+    /// should fall back to the suffix convention in that case).
+    pub(crate) fn resolve(&self, handle: &str, title: &str, variant: &str) -> Option<Vec<VariantOp>> {
+        let rule = self.rule_for(handle, title)?;
+
+        if !rule.variants.iter().any(|v| v.eq_ignore_ascii_case(variant)) {
+            return Some(vec![VariantOp::Hide]);
+        }
+
+        let mut ops = Vec::new();
+        if let Some(title) = &rule.rename {
+            ops.push(VariantOp::RenameTo(title.clone()));
+        }
+        if let Some(index) = rule.index {
+            ops.push(VariantOp::MoveToIndex(index));
+        }
+        Some(ops)
+    }
+
+    fn rule_for(&self, handle: &str, title: &str) -> Option<&TargetingRule> {
+        self.rules
+            .iter()
+            .find(|rule| rule.matcher == handle || title.contains(&rule.matcher))
+    }
+
+    /// All variants named across every rule, for validating title tags that
+    /// fall outside of any configured rule.
+    pub(crate) fn known_variants(&self) -> Vec<String> {
+        self.rules
+            .iter()
+            .flat_map(|rule| rule.variants.iter().cloned())
+            .collect()
+    }
+
+    /// Bucket weights for self-bucketing assignment: the configured
+    /// `weights` list, or equal weight across [`Self::known_variants`] when
+    /// it's absent - falling back further to
+    /// [`crate::run::DEFAULT_KNOWN_VARIANTS`] if the config names no
+    /// variants at all (e.g. `rules: []` with no `weights`), so an ordinary
+    /// self-bucketing-only config doesn't end up with an empty weight table
+    /// and leak the full option set.
+    ///
+    /// Labels are compared case-insensitively throughout this crate (e.g.
+    /// `resolve`'s `eq_ignore_ascii_case`), so deduping here sorts and
+    /// dedups by lowercased label too - a plain `.sort()`/`.dedup()` would
+    /// leave differently-cased spellings of the same variant (a plausible
+    /// merchant typo across rules) as separate buckets, doubling that
+    /// variant's effective weight.
+    pub(crate) fn assignment_weights(&self) -> Vec<(String, u32)> {
+        if !self.weights.is_empty() {
+            return self
+                .weights
+                .iter()
+                .map(|w| (w.variant.clone(), w.weight))
+                .collect();
+        }
+
+        let mut variants = self.known_variants();
+        if variants.is_empty() {
+            variants = crate::run::DEFAULT_KNOWN_VARIANTS
+                .iter()
+                .map(|s| s.to_string())
+                .collect();
+        }
+        variants.sort_by_key(|v| v.to_ascii_lowercase());
+        variants.dedup_by_key(|v| v.to_ascii_lowercase());
+        variants.into_iter().map(|variant| (variant, 1)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_valid_config() {
+        let raw = r#"{"rules": [{"match": "express", "variants": ["B"], "index": 0}]}"#;
+        let config = TargetingConfig::parse(raw).unwrap();
+        assert_eq!(config.rules.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_invalid_config_returns_none() {
+        assert!(TargetingConfig::parse("not json").is_none());
+    }
+
+    #[test]
+    fn test_resolve_hides_ineligible_variant() {
+        let config =
+            TargetingConfig::parse(r#"{"rules": [{"match": "express", "variants": ["B"]}]}"#)
+                .unwrap();
+
+        assert_eq!(
+            config.resolve("express", "Express Shipping", "A"),
+            Some(vec![VariantOp::Hide])
+        );
+    }
+
+    #[test]
+    fn test_resolve_renames_and_moves_eligible_variant() {
+        let config = TargetingConfig::parse(
+            r#"{"rules": [{"match": "express", "variants": ["B"], "index": 0, "rename": "Express"}]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.resolve("express", "Express Shipping", "B"),
+            Some(vec![
+                VariantOp::RenameTo("Express".to_string()),
+                VariantOp::MoveToIndex(0),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_resolve_matches_by_title_substring() {
+        let config =
+            TargetingConfig::parse(r#"{"rules": [{"match": "Standard", "variants": ["A"]}]}"#)
+                .unwrap();
+
+        assert_eq!(
+            config.resolve("standard-rate", "Standard Shipping", "A"),
+            Some(vec![])
+        );
+    }
+
+    #[test]
+    fn test_resolve_no_matching_rule_returns_none() {
+        let config = TargetingConfig::parse(r#"{"rules": []}"#).unwrap();
+        assert_eq!(config.resolve("standard", "Standard Shipping", "A"), None);
+    }
+
+    #[test]
+    fn test_assignment_weights_explicit() {
+        let config = TargetingConfig::parse(
+            r#"{"rules": [], "weights": [{"variant": "A", "weight": 1}, {"variant": "B", "weight": 3}]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.assignment_weights(),
+            vec![("A".to_string(), 1), ("B".to_string(), 3)]
+        );
+    }
+
+    #[test]
+    fn test_assignment_weights_defaults_to_equal_across_known_variants() {
+        let config = TargetingConfig::parse(
+            r#"{"rules": [{"match": "express", "variants": ["B", "A"]}]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.assignment_weights(),
+            vec![("A".to_string(), 1), ("B".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    fn test_assignment_weights_falls_back_to_defaults_when_config_names_no_variants() {
+        let config = TargetingConfig::parse(r#"{"rules": []}"#).unwrap();
+
+        assert_eq!(
+            config.assignment_weights(),
+            vec![
+                ("A".to_string(), 1),
+                ("B".to_string(), 1),
+                ("C".to_string(), 1),
+                ("D".to_string(), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_assignment_weights_dedups_case_insensitively_across_rules() {
+        let config = TargetingConfig::parse(
+            r#"{"rules": [{"match": "express", "variants": ["A", "B"]}, {"match": "standard", "variants": ["a", "C"]}]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.assignment_weights(),
+            vec![
+                ("A".to_string(), 1),
+                ("B".to_string(), 1),
+                ("C".to_string(), 1),
+            ]
+        );
+    }
+}
@@ -10,6 +10,9 @@
 //! 4. Function hides shipping rates that don't match the assigned variant
 //! 5. Visitor only sees their variant's shipping rate
 
+mod assign;
+mod config;
 pub mod run;
+mod tag;
 
 pub use run::*;
@@ -1,11 +1,17 @@
 //! Main function logic for the Shipping A/B Test Delivery Customization
 //!
 //! This function receives cart data and filters shipping options based on
-//! the visitor's assigned variant suffix stored in cart attributes.
+//! the visitor's assigned variant, stored in a cart attribute, and either a
+//! declarative targeting config or the title-tag convention (see
+//! [`config`](crate::config) and [`tag`](crate::tag)).
 
 use shopify_function::prelude::*;
 use shopify_function::Result;
 
+use crate::assign;
+use crate::config::TargetingConfig;
+use crate::tag;
+
 // Generated from run.graphql
 generate_types!(
     query_path = "./src/run.graphql",
@@ -19,39 +25,51 @@ generate_types!(
 /// visitor's assigned A/B test variant.
 #[shopify_function]
 fn run(input: input::ResponseData) -> Result<output::FunctionRunResult> {
-    // Get the shipping variant suffix from cart attributes
-    let variant_suffix = input
+    // Declarative targeting config, read from the function configuration
+    // metafield. Absent or invalid config means every option falls back to
+    // the title-tag convention, and assignment falls back to equal weights.
+    let config = input
+        .shop
+        .metafield
+        .as_ref()
+        .and_then(|metafield| metafield.value.as_ref())
+        .and_then(|raw| TargetingConfig::parse(raw));
+
+    let known_variants = known_variant_labels(config.as_ref());
+
+    // Explicit variant from the cart attribute, if the storefront already
+    // assigned one.
+    let explicit_variant = input
         .cart
         .attribute
         .as_ref()
         .and_then(|attr| attr.value.as_ref())
         .map(|v| v.as_str())
-        .unwrap_or("");
+        .filter(|v| !v.is_empty());
 
-    // If no variant assigned, return all rates unchanged
-    if variant_suffix.is_empty() {
-        return Ok(output::FunctionRunResult { operations: vec![] });
-    }
+    let variant = match explicit_variant {
+        Some(variant) => variant.to_string(),
+        None => match self_bucket(&input.cart, config.as_ref(), &known_variants) {
+            Some(variant) => variant,
+            // No variant attribute and no cart-stable identifier to bucket
+            // on (fully anonymous cart): show every rate unchanged.
+            None => return Ok(output::FunctionRunResult { operations: vec![] }),
+        },
+    };
+    let variant = variant.as_str();
 
-    // Build list of operations to hide non-matching rates
+    // Build list of operations (hide/move/rename) for the assigned variant
     let mut operations = vec![];
 
     for delivery_group in &input.cart.delivery_groups {
         for option in &delivery_group.delivery_options {
-            // Check if this rate matches the assigned variant
-            let rate_suffix = extract_suffix(&option.title);
-
-            // Only hide rates that have a suffix and don't match
-            // Rates without a suffix (e.g., "Express Shipping") are always shown
-            if !rate_suffix.is_empty() && rate_suffix != variant_suffix {
-                // Hide this rate - it's for a different variant
-                operations.push(output::Operation {
-                    hide: Some(output::HideOperation {
-                        delivery_option_handle: option.handle.clone(),
-                    }),
-                    move_: None,
-                    rename: None,
-                });
+            let ops = config
+                .as_ref()
+                .and_then(|config| config.resolve(&option.handle, &option.title, variant))
+                .unwrap_or_else(|| resolve_variant_ops(&option.title, variant, &known_variants));
+
+            for op in ops {
+                operations.push(build_operation(&option.handle, op));
             }
         }
     }
@@ -59,57 +77,203 @@ fn run(input: input::ResponseData) -> Result<output::FunctionRunResult> {
     Ok(output::FunctionRunResult { operations })
 }
 
-/// Extract suffix from rate name like "Standard Shipping (A)" -> "A"
+/// Deterministically buckets a cart with no explicit variant attribute into
+/// one of the configured (or default) variants.
 ///
-/// Returns empty string if no suffix found.
+/// Returns `None` when the cart carries no stable identifier to bucket on,
+/// or the weight table carries no weight (every configured weight is zero).
+fn self_bucket(
+    cart: &input::Cart,
+    config: Option<&TargetingConfig>,
+    known_variants: &[String],
+) -> Option<String> {
+    let identifier = cart_identifier(cart)?;
+
+    let weights = match config {
+        Some(config) => config.assignment_weights(),
+        None => known_variants.iter().cloned().map(|v| (v, 1)).collect(),
+    };
+
+    assign::assign_variant(&identifier, &weights).map(str::to_string)
+}
+
+/// A cart-stable identifier to bucket on: the buyer's customer id if
+/// they're logged in, falling back to their email.
+fn cart_identifier(cart: &input::Cart) -> Option<String> {
+    let buyer_identity = cart.buyer_identity.as_ref()?;
+    buyer_identity
+        .customer
+        .as_ref()
+        .map(|customer| customer.id.clone())
+        .or_else(|| buyer_identity.email.clone())
+}
+
+/// Variant labels accepted as tags on a title, used by the fallback
+/// title-tag convention (the config, when present, declares its own
+/// variants per rule and doesn't need this list). Also used as the
+/// fallback bucket set for self-bucketing assignment when a config
+/// declares no variants anywhere (see
+/// [`TargetingConfig::assignment_weights`](crate::config::TargetingConfig::assignment_weights)).
+pub(crate) const DEFAULT_KNOWN_VARIANTS: &[&str] = &["A", "B", "C", "D"];
+
+/// The known-variant set used to validate a parsed title tag: the config's
+/// declared rule variants when it has any, otherwise [`DEFAULT_KNOWN_VARIANTS`].
 ///
-/// # Examples
-/// ```
-/// assert_eq!(extract_suffix("Standard Shipping (A)"), "A");
-/// assert_eq!(extract_suffix("Free Shipping (B)"), "B");
-/// assert_eq!(extract_suffix("Express Shipping"), "");
-/// ```
-fn extract_suffix(title: &str) -> &str {
-    // Look for pattern "(X)" at the end of the title
-    if let Some(start) = title.rfind('(') {
-        if let Some(end) = title.rfind(')') {
-            if start < end && end == title.len() - 1 {
-                let suffix = &title[start + 1..end];
-                // Only return valid single-letter suffixes (A, B, C, D)
-                if suffix.len() == 1 && matches!(suffix, "A" | "B" | "C" | "D") {
-                    return suffix;
-                }
-            }
-        }
+/// A config that declares only `weights` (a standalone self-bucketing setup
+/// with no per-option rules) still falls back to the default list here -
+/// `tag::parse` treats an empty `known_labels` as "accept anything", so an
+/// empty list would make any bracketed text in any title read as a real
+/// variant tag.
+///
+/// Sorted by lowercased label so [`tag::contains_ci`] can binary search it -
+/// a plain `.sort()` would order by case-sensitive `Ord` and desync from
+/// `contains_ci`'s case-insensitive lookup.
+fn known_variant_labels(config: Option<&TargetingConfig>) -> Vec<String> {
+    let rule_variants = config.map(TargetingConfig::known_variants).unwrap_or_default();
+
+    let mut labels = if rule_variants.is_empty() {
+        DEFAULT_KNOWN_VARIANTS.iter().map(|s| s.to_string()).collect()
+    } else {
+        rule_variants
+    };
+    labels.sort_by_key(|label| label.to_ascii_lowercase());
+    labels.dedup_by_key(|label| label.to_ascii_lowercase());
+    labels
+}
+
+/// Per-option action derived from the variant rule model.
+///
+/// `Hide` removes the option from checkout entirely, `MoveToIndex` reorders
+/// it within its delivery group, and `RenameTo` relabels it - used to strip
+/// the internal variant tag so the visitor never sees e.g. "(A)" in
+/// checkout.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum VariantOp {
+    Hide,
+    MoveToIndex(usize),
+    RenameTo(String),
+}
+
+/// Resolves the list of operations to apply to a rate's title for the given
+/// variant, under the plain tag convention (no targeting config).
+///
+/// Rates tagged for a different variant are hidden. Rates tagged for the
+/// current variant are renamed to drop the tag. Untagged rates, and rates
+/// whose tag doesn't name a known variant, are left untouched. Reordering
+/// isn't expressible under this convention - it requires a targeting config
+/// rule's `index` (see [`TargetingConfig::resolve`](crate::config::TargetingConfig::resolve)).
+fn resolve_variant_ops(title: &str, variant: &str, known_variants: &[String]) -> Vec<VariantOp> {
+    let labels = tag::parse(title, known_variants);
+
+    if labels.is_empty() {
+        return Vec::new();
+    }
+
+    if !labels.iter().any(|label| label.eq_ignore_ascii_case(variant)) {
+        return vec![VariantOp::Hide];
+    }
+
+    vec![VariantOp::RenameTo(tag::strip(title))]
+}
+
+/// Builds the `output::Operation` for a resolved [`VariantOp`].
+fn build_operation(delivery_option_handle: &str, op: VariantOp) -> output::Operation {
+    match op {
+        VariantOp::Hide => output::Operation {
+            hide: Some(output::HideOperation {
+                delivery_option_handle: delivery_option_handle.to_string(),
+            }),
+            move_: None,
+            rename: None,
+        },
+        VariantOp::MoveToIndex(index) => output::Operation {
+            hide: None,
+            move_: Some(output::MoveOperation {
+                delivery_option_handle: delivery_option_handle.to_string(),
+                index: index as i64,
+            }),
+            rename: None,
+        },
+        VariantOp::RenameTo(title) => output::Operation {
+            hide: None,
+            move_: None,
+            rename: Some(output::RenameOperation {
+                delivery_option_handle: delivery_option_handle.to_string(),
+                title,
+            }),
+        },
     }
-    ""
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn known() -> Vec<String> {
+        DEFAULT_KNOWN_VARIANTS.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_resolve_variant_ops_hides_other_variant() {
+        assert_eq!(
+            resolve_variant_ops("Standard Shipping (B)", "A", &known()),
+            vec![VariantOp::Hide]
+        );
+    }
+
     #[test]
-    fn test_extract_suffix_valid() {
-        assert_eq!(extract_suffix("Standard Shipping (A)"), "A");
-        assert_eq!(extract_suffix("Free Shipping (B)"), "B");
-        assert_eq!(extract_suffix("Express (C)"), "C");
-        assert_eq!(extract_suffix("Overnight (D)"), "D");
+    fn test_resolve_variant_ops_renames_own_variant() {
+        assert_eq!(
+            resolve_variant_ops("Standard Shipping (A)", "A", &known()),
+            vec![VariantOp::RenameTo("Standard Shipping".to_string())]
+        );
     }
 
     #[test]
-    fn test_extract_suffix_invalid() {
-        assert_eq!(extract_suffix("Standard Shipping"), "");
-        assert_eq!(extract_suffix("Free Shipping ()"), "");
-        assert_eq!(extract_suffix("Express (AB)"), "");
-        assert_eq!(extract_suffix("Overnight (E)"), "");
-        assert_eq!(extract_suffix("Test (1)"), "");
+    fn test_resolve_variant_ops_renames_own_variant_no_promotion() {
+        assert_eq!(
+            resolve_variant_ops("Express Shipping (B)", "B", &known()),
+            vec![VariantOp::RenameTo("Express Shipping".to_string())]
+        );
     }
 
     #[test]
-    fn test_extract_suffix_edge_cases() {
-        assert_eq!(extract_suffix(""), "");
-        assert_eq!(extract_suffix("(A)"), "A");
-        assert_eq!(extract_suffix("Multiple (X) parts (A)"), "A");
+    fn test_resolve_variant_ops_untagged_is_noop() {
+        assert!(resolve_variant_ops("Free Shipping", "A", &known()).is_empty());
+        assert!(resolve_variant_ops("Free Shipping", "B", &known()).is_empty());
+    }
+
+    #[test]
+    fn test_resolve_variant_ops_unknown_tag_is_noop() {
+        assert!(resolve_variant_ops("Overnight (Z)", "A", &known()).is_empty());
+    }
+
+    #[test]
+    fn test_known_variant_labels_defaults_when_no_config() {
+        assert_eq!(known_variant_labels(None), vec!["A", "B", "C", "D"]);
+    }
+
+    #[test]
+    fn test_known_variant_labels_falls_back_when_config_has_weights_only() {
+        let config = TargetingConfig::parse(
+            r#"{"rules": [], "weights": [{"variant": "A"}, {"variant": "B"}]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(known_variant_labels(Some(&config)), vec!["A", "B", "C", "D"]);
+    }
+
+    #[test]
+    fn test_known_variant_labels_sorted_case_insensitively() {
+        let config = TargetingConfig::parse(
+            r#"{"rules": [{"match": "express", "variants": ["B", "Control", "a"]}]}"#,
+        )
+        .unwrap();
+        let known = known_variant_labels(Some(&config));
+
+        assert!(tag::contains_ci(&known, "b"));
+        assert!(tag::contains_ci(&known, "CONTROL"));
+        assert!(tag::contains_ci(&known, "A"));
+        assert!(!tag::contains_ci(&known, "Z"));
     }
 }